@@ -1,19 +1,277 @@
 use anchor_lang::prelude::*;
 
+// Bitmask flags for `TokenBadge::allowed_extensions`. Each bit authorizes a
+// single Token-2022 mint/account extension for use by pools referencing this
+// badge's `token_mint`.
+pub const TOKEN_BADGE_EXTENSION_TRANSFER_FEE: u64 = 1 << 0;
+pub const TOKEN_BADGE_EXTENSION_TRANSFER_HOOK: u64 = 1 << 1;
+pub const TOKEN_BADGE_EXTENSION_PERMANENT_DELEGATE: u64 = 1 << 2;
+pub const TOKEN_BADGE_EXTENSION_CONFIDENTIAL_TRANSFER: u64 = 1 << 3;
+pub const TOKEN_BADGE_EXTENSION_INTEREST_BEARING: u64 = 1 << 4;
+
+// Layout versions understood by `TokenBadge::migrate`. `VERSION_0` accounts
+// predate the `version` field and the `allowed_extensions` bitmask, and
+// `VERSION_1` accounts predate `badge_authority`/`revoked`, so the reserve
+// bytes backing those fields are zero and must be reinterpreted, not
+// trusted as-is.
+pub const TOKEN_BADGE_VERSION_0: u8 = 0;
+pub const TOKEN_BADGE_VERSION_1: u8 = 1;
+pub const TOKEN_BADGE_CURRENT_VERSION: u8 = 2;
+
+#[error_code]
+pub enum TokenBadgeError {
+    #[msg("Badge authority cannot be the default public key")]
+    InvalidBadgeAuthority,
+}
+
 #[account]
 #[derive(Default)]
 pub struct TokenBadge {
     pub whirlpools_config: Pubkey, // 32
     pub token_mint: Pubkey,        // 32
-                                   // 128 RESERVE
+    pub version: u8,               // 1
+    pub allowed_extensions: u64,   // 8
+    pub badge_authority: Pubkey,   // 32
+    pub revoked: bool,             // 1
+                                   // 86 RESERVE
 }
 
 impl TokenBadge {
-    pub const LEN: usize = 8 + 32 + 32 + 128;
+    pub const LEN: usize = 8 + 32 + 32 + 1 + 8 + 32 + 1 + 86;
+
+    pub fn initialize(
+        &mut self,
+        whirlpools_config: Pubkey,
+        token_mint: Pubkey,
+        allowed_extensions: u64,
+        badge_authority: Pubkey,
+    ) -> Result<()> {
+        require_keys_neq!(
+            badge_authority,
+            Pubkey::default(),
+            TokenBadgeError::InvalidBadgeAuthority
+        );
 
-    pub fn initialize(&mut self, whirlpools_config: Pubkey, token_mint: Pubkey) -> Result<()> {
         self.whirlpools_config = whirlpools_config;
         self.token_mint = token_mint;
+        self.version = TOKEN_BADGE_CURRENT_VERSION;
+        self.allowed_extensions = allowed_extensions;
+        self.badge_authority = badge_authority;
+        self.revoked = false;
+        Ok(())
+    }
+
+    pub fn update_allowed_extensions(&mut self, allowed_extensions: u64) -> Result<()> {
+        self.allowed_extensions = allowed_extensions;
+        Ok(())
+    }
+
+    pub fn is_extension_allowed(&self, extension: u64) -> bool {
+        self.allowed_extensions & extension == extension
+    }
+
+    // Returns whether `signer` is this badge's `badge_authority`; the global
+    // config authority gets no special standing here. `set_authority`,
+    // `revoke` and `update_allowed_extensions` do not call this themselves,
+    // so the caller checks the signer before mutating the badge.
+    pub fn is_authorized(&self, signer: Pubkey) -> bool {
+        self.badge_authority == signer
+    }
+
+    pub fn set_authority(&mut self, new_authority: Pubkey) -> Result<()> {
+        // No real transaction signer can ever equal the default (all-zero)
+        // pubkey, so accepting it here would permanently brick the badge:
+        // no one could ever pass `is_authorized` again to fix it.
+        require_keys_neq!(
+            new_authority,
+            Pubkey::default(),
+            TokenBadgeError::InvalidBadgeAuthority
+        );
+
+        self.badge_authority = new_authority;
         Ok(())
     }
+
+    // Retires the badge without deleting it, e.g. when the mint is later
+    // found malicious. Pool-init code must treat a revoked badge as if it
+    // did not exist.
+    pub fn revoke(&mut self) -> Result<()> {
+        self.revoked = true;
+        Ok(())
+    }
+
+    // Upgrades an on-disk account from an older layout to
+    // `TOKEN_BADGE_CURRENT_VERSION`, back-filling newly interpreted reserve
+    // fields with safe defaults. No-op once the account is already current.
+    pub fn migrate(&mut self) -> Result<()> {
+        if self.version == TOKEN_BADGE_VERSION_0 {
+            // v0 accounts never wrote into what is now `allowed_extensions`,
+            // so that reserve region is guaranteed zero. Back-fill it
+            // explicitly rather than relying on the zeroed bytes, and default
+            // to no extensions allowed until an authority opts in.
+            self.allowed_extensions = 0;
+            self.version = TOKEN_BADGE_VERSION_1;
+        }
+
+        if self.version == TOKEN_BADGE_VERSION_1 {
+            // v1 accounts predate delegated badge authorities. Default the
+            // new `badge_authority` field to the address of the
+            // `whirlpools_config` account this badge belongs to (the account
+            // key itself, not an authority key stored in that config), and
+            // leave the badge active.
+            self.badge_authority = self.whirlpools_config;
+            self.revoked = false;
+            self.version = TOKEN_BADGE_CURRENT_VERSION;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Builds the raw account bytes a v0 `TokenBadge` (predating `version` and
+    // `allowed_extensions`) would have on-chain: discriminator, the two
+    // original fields, then an all-zero reserve out to `TokenBadge::LEN`.
+    fn v0_buffer(whirlpools_config: Pubkey, token_mint: Pubkey) -> Vec<u8> {
+        let mut data = TokenBadge::discriminator().to_vec();
+        data.extend_from_slice(&whirlpools_config.to_bytes());
+        data.extend_from_slice(&token_mint.to_bytes());
+        data.resize(TokenBadge::LEN, 0);
+        data
+    }
+
+    // Builds the raw account bytes a v1 `TokenBadge` (after chunk0-2, before
+    // `badge_authority`/`revoked`) would have on-chain.
+    fn v1_buffer(whirlpools_config: Pubkey, token_mint: Pubkey, allowed_extensions: u64) -> Vec<u8> {
+        let mut data = TokenBadge::discriminator().to_vec();
+        data.extend_from_slice(&whirlpools_config.to_bytes());
+        data.extend_from_slice(&token_mint.to_bytes());
+        data.push(TOKEN_BADGE_VERSION_1);
+        data.extend_from_slice(&allowed_extensions.to_le_bytes());
+        data.resize(TokenBadge::LEN, 0);
+        data
+    }
+
+    #[test]
+    fn len_matches_original_reserve_budget() {
+        // Pins the account's total declared size so a future reserve/LEN
+        // miscalculation fails here instead of shipping and needing a
+        // follow-up fix commit.
+        assert_eq!(TokenBadge::LEN, 200);
+    }
+
+    #[test]
+    fn migrate_v0_buffer_backfills_allowed_extensions_and_authority() {
+        let whirlpools_config = Pubkey::new_unique();
+        let token_mint = Pubkey::new_unique();
+        let data = v0_buffer(whirlpools_config, token_mint);
+
+        let mut slice: &[u8] = &data;
+        let mut badge = TokenBadge::try_deserialize(&mut slice).unwrap();
+        assert_eq!(badge.version, TOKEN_BADGE_VERSION_0);
+
+        badge.migrate().unwrap();
+
+        assert_eq!(badge.version, TOKEN_BADGE_CURRENT_VERSION);
+        assert_eq!(badge.allowed_extensions, 0);
+        assert_eq!(badge.badge_authority, whirlpools_config);
+        assert!(!badge.revoked);
+    }
+
+    #[test]
+    fn migrate_v1_buffer_backfills_authority() {
+        let whirlpools_config = Pubkey::new_unique();
+        let token_mint = Pubkey::new_unique();
+        let data = v1_buffer(
+            whirlpools_config,
+            token_mint,
+            TOKEN_BADGE_EXTENSION_TRANSFER_FEE,
+        );
+
+        let mut slice: &[u8] = &data;
+        let mut badge = TokenBadge::try_deserialize(&mut slice).unwrap();
+        assert_eq!(badge.version, TOKEN_BADGE_VERSION_1);
+
+        badge.migrate().unwrap();
+
+        assert_eq!(badge.version, TOKEN_BADGE_CURRENT_VERSION);
+        assert_eq!(badge.allowed_extensions, TOKEN_BADGE_EXTENSION_TRANSFER_FEE);
+        assert_eq!(badge.badge_authority, whirlpools_config);
+        assert!(!badge.revoked);
+    }
+
+    #[test]
+    fn migrate_is_idempotent_for_current_version() {
+        let badge_authority = Pubkey::new_unique();
+        let mut badge = TokenBadge {
+            whirlpools_config: Pubkey::new_unique(),
+            token_mint: Pubkey::new_unique(),
+            version: TOKEN_BADGE_CURRENT_VERSION,
+            allowed_extensions: TOKEN_BADGE_EXTENSION_TRANSFER_FEE,
+            badge_authority,
+            revoked: false,
+        };
+
+        badge.migrate().unwrap();
+
+        assert_eq!(badge.version, TOKEN_BADGE_CURRENT_VERSION);
+        assert_eq!(badge.allowed_extensions, TOKEN_BADGE_EXTENSION_TRANSFER_FEE);
+        assert_eq!(badge.badge_authority, badge_authority);
+        assert!(!badge.revoked);
+    }
+
+    #[test]
+    fn set_authority_changes_who_is_authorized() {
+        let original_authority = Pubkey::new_unique();
+        let new_authority = Pubkey::new_unique();
+        let mut badge = TokenBadge {
+            badge_authority: original_authority,
+            ..TokenBadge::default()
+        };
+
+        assert!(badge.is_authorized(original_authority));
+        assert!(!badge.is_authorized(new_authority));
+
+        badge.set_authority(new_authority).unwrap();
+
+        assert!(!badge.is_authorized(original_authority));
+        assert!(badge.is_authorized(new_authority));
+    }
+
+    #[test]
+    fn set_authority_rejects_default_pubkey() {
+        let mut badge = TokenBadge {
+            badge_authority: Pubkey::new_unique(),
+            ..TokenBadge::default()
+        };
+
+        assert!(badge.set_authority(Pubkey::default()).is_err());
+    }
+
+    #[test]
+    fn initialize_rejects_default_badge_authority() {
+        let mut badge = TokenBadge::default();
+
+        let result = badge.initialize(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            Pubkey::default(),
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn revoke_marks_badge_revoked() {
+        let mut badge = TokenBadge::default();
+        assert!(!badge.revoked);
+
+        badge.revoke().unwrap();
+
+        assert!(badge.revoked);
+    }
 }